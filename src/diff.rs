@@ -0,0 +1,139 @@
+// This file is part of vrename.
+// Copyright (C) 2024 John DiSanti.
+//
+// vrename is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// vrename is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with vrename.
+// If not, see <https://www.gnu.org/licenses/>.
+
+//! Formats a unified-diff-style preview of a rename batch for `--dry-run`.
+//!
+//! The name file always has exactly one line per input file, so the buffer before and
+//! after editing line up one-to-one: line `i` either didn't change, or it's a `-old`/
+//! `+new` pair. This renders those pairs as unified-diff hunks with a few lines of
+//! surrounding context, the same way `rustfmt --check` or `sd` preview their edits.
+
+use std::fmt::Write as _;
+
+/// Number of unchanged lines to show around each run of changes.
+const CONTEXT_LINES: usize = 3;
+
+/// Renders the name-file buffer before (`old_lines`) and after (`new_lines`) editing as
+/// a unified-diff-style block. `old_lines` and `new_lines` must be the same length.
+pub fn unified_diff(old_lines: &[String], new_lines: &[String]) -> String {
+    debug_assert_eq!(old_lines.len(), new_lines.len());
+
+    let changed = old_lines
+        .iter()
+        .zip(new_lines)
+        .map(|(old, new)| old != new)
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+    let mut i = 0;
+    while i < changed.len() {
+        if !changed[i] {
+            i += 1;
+            continue;
+        }
+
+        let hunk_start = i.saturating_sub(CONTEXT_LINES);
+        let hunk_end = extend_hunk(&changed, i);
+
+        let _ = writeln!(
+            out,
+            "@@ -{},{len} +{},{len} @@",
+            hunk_start + 1,
+            hunk_start + 1,
+            len = hunk_end - hunk_start
+        );
+        for idx in hunk_start..hunk_end {
+            if changed[idx] {
+                let _ = writeln!(out, "-{}", old_lines[idx]);
+                let _ = writeln!(out, "+{}", new_lines[idx]);
+            } else {
+                let _ = writeln!(out, " {}", old_lines[idx]);
+            }
+        }
+
+        i = hunk_end;
+    }
+
+    out
+}
+
+/// Grows a hunk starting at `first_change`, pulling in later runs of changes whose
+/// surrounding context would otherwise overlap this one's trailing context, and
+/// returns the exclusive end of the hunk (the last change plus its trailing context).
+fn extend_hunk(changed: &[bool], first_change: usize) -> usize {
+    let mut last_change = first_change;
+    let mut idx = first_change + 1;
+    while idx < changed.len() {
+        if changed[idx] {
+            last_change = idx;
+            idx += 1;
+            continue;
+        }
+        let unchanged_run_start = idx;
+        while idx < changed.len() && !changed[idx] {
+            idx += 1;
+        }
+        let gap = idx - unchanged_run_start;
+        if idx < changed.len() && gap < 2 * CONTEXT_LINES {
+            last_change = idx;
+            idx += 1;
+        } else {
+            break;
+        }
+    }
+    (last_change + 1 + CONTEXT_LINES).min(changed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn no_changes_produces_no_hunks() {
+        let old = lines(&["a", "b", "c"]);
+        assert_eq!("", unified_diff(&old, &old));
+    }
+
+    #[test]
+    fn single_change_includes_surrounding_context() {
+        let old = lines(&["a", "b", "c", "d", "e"]);
+        let new = lines(&["a", "b", "z", "d", "e"]);
+        assert_eq!(
+            "@@ -1,5 +1,5 @@\n a\n b\n-c\n+z\n d\n e\n",
+            unified_diff(&old, &new)
+        );
+    }
+
+    #[test]
+    fn distant_changes_get_separate_hunks() {
+        let old = lines(&["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"]);
+        let new = lines(&["a", "x", "c", "d", "e", "f", "g", "h", "y", "j"]);
+        let diff = unified_diff(&old, &new);
+        assert_eq!(2, diff.matches("@@").count() / 2);
+        assert!(diff.contains("-b\n+x"));
+        assert!(diff.contains("-i\n+y"));
+    }
+
+    #[test]
+    fn nearby_changes_merge_into_one_hunk() {
+        let old = lines(&["a", "b", "c", "d", "e"]);
+        let new = lines(&["z", "b", "c", "d", "y"]);
+        let diff = unified_diff(&old, &new);
+        assert_eq!(1, diff.matches("@@").count() / 2);
+    }
+}
@@ -0,0 +1,100 @@
+// This file is part of vrename.
+// Copyright (C) 2024 John DiSanti.
+//
+// vrename is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// vrename is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with vrename.
+// If not, see <https://www.gnu.org/licenses/>.
+
+//! Resolves the user's preferred text editor.
+//!
+//! Mirrors the fallback chain used by other editor-launching tools: `$VISUAL`, then
+//! `$EDITOR`, then git's configured `core.editor`, then finally `vi`.
+
+use anyhow::{anyhow, Result};
+use std::process::Command;
+
+/// A resolved editor command: a program plus any arguments it was configured with
+/// (e.g. `code --wait`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl EditorCommand {
+    /// Resolves the editor to use, preferring (in order) `$VISUAL`, `$EDITOR`, git's
+    /// `core.editor` config, and finally falling back to `vi`.
+    pub fn resolve() -> Result<Self> {
+        let command = env_var("VISUAL")
+            .or_else(|| env_var("EDITOR"))
+            .or_else(git_core_editor)
+            .unwrap_or_else(|| "vi".to_string());
+        Self::parse(&command)
+    }
+
+    /// Splits an editor command string (e.g. `code --wait`) into a program and its
+    /// arguments.
+    fn parse(command: &str) -> Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| anyhow!("editor command is empty"))?
+            .to_string();
+        Ok(Self {
+            program,
+            args: parts.map(str::to_string).collect(),
+        })
+    }
+}
+
+/// Reads an environment variable, treating unset and blank the same way.
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Reads git's configured editor, if any.
+fn git_core_editor() -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "--get", "core.editor"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let value = String::from_utf8(output.stdout).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_program_without_args() {
+        let command = EditorCommand::parse("vi").unwrap();
+        assert_eq!("vi", command.program);
+        assert!(command.args.is_empty());
+    }
+
+    #[test]
+    fn parses_program_with_args() {
+        let command = EditorCommand::parse("code --wait").unwrap();
+        assert_eq!("code", command.program);
+        assert_eq!(vec!["--wait".to_string()], command.args);
+    }
+
+    #[test]
+    fn rejects_an_empty_command() {
+        assert!(EditorCommand::parse("   ").is_err());
+    }
+}
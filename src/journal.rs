@@ -0,0 +1,278 @@
+// This file is part of vrename.
+// Copyright (C) 2024 John DiSanti.
+//
+// vrename is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// vrename is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with vrename.
+// If not, see <https://www.gnu.org/licenses/>.
+
+//! Persists every successful batch of renames to an append-only journal, so `--undo`
+//! has a durable history to roll back the most recent one from.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// One journaled batch: the directory it ran in, and its old -> new rename pairs, as
+/// the user actually typed them (not the expanded, temp-file-routed execution order a
+/// cycle might need — that's an implementation detail of [`crate::plan::RenamePlan`]
+/// that doesn't outlive the batch that created it).
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub dir: String,
+    pub renames: Vec<(String, String)>,
+}
+
+/// Appends a successfully-applied batch of renames to the journal. Does nothing if
+/// `name_map` has no actual renames in it, since there's nothing to undo later.
+pub fn record(name_map: &HashMap<String, String>) -> Result<()> {
+    record_to(&journal_path()?, name_map)
+}
+
+/// Returns the most recently journaled entry, if any, without removing it. Callers
+/// should only remove it (via [`remove_last_entry`]) once they've successfully
+/// reversed it, so a failed `--undo` leaves the history intact to retry.
+pub fn peek_last_entry() -> Result<Option<JournalEntry>> {
+    peek_last_entry_from(&journal_path()?)
+}
+
+/// Removes the most recently journaled entry. Does nothing if the journal is empty.
+pub fn remove_last_entry() -> Result<()> {
+    remove_last_entry_from(&journal_path()?)
+}
+
+fn record_to(path: &Path, name_map: &HashMap<String, String>) -> Result<()> {
+    let mut renames = name_map
+        .iter()
+        .filter(|(old_name, new_name)| old_name != new_name)
+        .map(|(old_name, new_name)| (old_name.clone(), new_name.clone()))
+        .collect::<Vec<_>>();
+    if renames.is_empty() {
+        return Ok(());
+    }
+    renames.sort();
+
+    let entry = JournalEntry {
+        dir: current_dir_string()?,
+        renames,
+    };
+    let line = serde_json::to_string(&entry)
+        .map_err(|err| anyhow!("failed to serialize journal entry: {err}"))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| anyhow!("failed to create journal directory {parent:?}: {err}"))?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| anyhow!("failed to open journal file {path:?}: {err}"))?;
+    std::io::Write::write_all(&mut file, format!("{line}\n").as_bytes())
+        .map_err(|err| anyhow!("failed to append to journal file {path:?}: {err}"))?;
+    Ok(())
+}
+
+fn peek_last_entry_from(path: &Path) -> Result<Option<JournalEntry>> {
+    let Some(last_line) = last_journal_line(path)? else {
+        return Ok(None);
+    };
+    let entry: JournalEntry = serde_json::from_str(&last_line)
+        .map_err(|err| anyhow!("failed to parse journal entry {last_line:?}: {err}"))?;
+    Ok(Some(entry))
+}
+
+fn remove_last_entry_from(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let contents = fs::read_to_string(path)
+        .map_err(|err| anyhow!("failed to read journal file {path:?}: {err}"))?;
+    let mut lines = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    if lines.pop().is_none() {
+        return Ok(());
+    }
+
+    let remaining = if lines.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", lines.join("\n"))
+    };
+    fs::write(path, remaining)
+        .map_err(|err| anyhow!("failed to rewrite journal file {path:?}: {err}"))?;
+    Ok(())
+}
+
+fn last_journal_line(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(path)
+        .map_err(|err| anyhow!("failed to read journal file {path:?}: {err}"))?;
+    Ok(contents
+        .lines()
+        .rfind(|line| !line.trim().is_empty())
+        .map(str::to_string))
+}
+
+fn current_dir_string() -> Result<String> {
+    Ok(std::env::current_dir()
+        .map_err(|err| anyhow!("failed to determine current directory: {err}"))?
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Path to the journal file, under `$XDG_STATE_HOME/vrename` or `~/.local/state/vrename`.
+fn journal_path() -> Result<PathBuf> {
+    let state_dir = std::env::var("XDG_STATE_HOME").map(PathBuf::from).or_else(|_| {
+        std::env::var("HOME")
+            .map(|home| PathBuf::from(home).join(".local/state"))
+            .map_err(|_| anyhow!("could not determine state directory (set XDG_STATE_HOME or HOME)"))
+    })?;
+    Ok(state_dir.join("vrename").join("journal.jsonl"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::RenamePlan;
+    use std::fs as stdfs;
+
+    fn name_map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(old_name, new_name)| (old_name.to_string(), new_name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn peek_last_entry_from_missing_file_is_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        assert_eq!(None, peek_last_entry_from(&path).unwrap());
+    }
+
+    #[test]
+    fn records_then_peeks_and_removes_the_last_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("journal.jsonl");
+
+        record_to(&path, &name_map(&[("a", "b")])).unwrap();
+        record_to(&path, &name_map(&[("c", "d"), ("e", "f")])).unwrap();
+
+        // Peeking doesn't consume the entry.
+        let entry = peek_last_entry_from(&path).unwrap().unwrap();
+        assert_eq!(
+            vec![("c".to_string(), "d".to_string()), ("e".to_string(), "f".to_string())],
+            entry.renames
+        );
+        assert_eq!(entry, peek_last_entry_from(&path).unwrap().unwrap());
+
+        remove_last_entry_from(&path).unwrap();
+        let entry = peek_last_entry_from(&path).unwrap().unwrap();
+        assert_eq!(vec![("a".to_string(), "b".to_string())], entry.renames);
+
+        remove_last_entry_from(&path).unwrap();
+        assert_eq!(None, peek_last_entry_from(&path).unwrap());
+    }
+
+    #[test]
+    fn removing_from_a_missing_or_empty_journal_is_a_no_op() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        remove_last_entry_from(&path).unwrap();
+    }
+
+    #[test]
+    fn recording_an_empty_batch_is_a_no_op() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        record_to(&path, &HashMap::new()).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn recording_filters_out_unchanged_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        record_to(&path, &name_map(&[("a", "a")])).unwrap();
+        assert!(!path.exists());
+    }
+
+    /// Applies `name_map` to real files in a tempdir via the same [`RenamePlan`] the
+    /// forward rename uses, records it, then undoes it by peeking the entry, reversing
+    /// it through a fresh `RenamePlan`, and only removing the entry once every rename
+    /// in the reversal has actually succeeded. Covers the case the review called out:
+    /// the journal must hold the semantic pairs the user typed, not the expanded
+    /// cycle-breaking steps, since those reference a temp file that's long gone by the
+    /// time `--undo` runs.
+    fn round_trips_through_record_and_undo(pairs: &[(&str, &str)]) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let original_contents = |name: &str| format!("contents of {name}");
+
+        let name_map = pairs
+            .iter()
+            .map(|(old_name, new_name)| {
+                let old_path = dir.path().join(old_name);
+                stdfs::write(&old_path, original_contents(old_name)).unwrap();
+                (
+                    old_path.to_string_lossy().into_owned(),
+                    dir.path().join(new_name).to_string_lossy().into_owned(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        let plan = RenamePlan::new(&name_map).unwrap();
+        for step in &plan.steps {
+            stdfs::rename(&step.old_name, &step.new_name).unwrap();
+        }
+        record_to(&path, &name_map).unwrap();
+
+        let entry = peek_last_entry_from(&path).unwrap().unwrap();
+        let reversed_map = entry
+            .renames
+            .iter()
+            .cloned()
+            .map(|(old_name, new_name)| (new_name, old_name))
+            .collect::<HashMap<_, _>>();
+        let undo_plan = RenamePlan::new(&reversed_map).unwrap();
+        for step in &undo_plan.steps {
+            stdfs::rename(&step.old_name, &step.new_name).unwrap();
+        }
+        remove_last_entry_from(&path).unwrap();
+
+        for (old_name, _) in pairs {
+            let restored = stdfs::read_to_string(dir.path().join(old_name)).unwrap();
+            assert_eq!(original_contents(old_name), restored);
+        }
+        assert_eq!(None, peek_last_entry_from(&path).unwrap());
+    }
+
+    #[test]
+    fn round_trips_a_two_node_swap_through_record_and_undo() {
+        round_trips_through_record_and_undo(&[("a.txt", "b.txt"), ("b.txt", "a.txt")]);
+    }
+
+    #[test]
+    fn round_trips_a_three_node_rotation_through_record_and_undo() {
+        round_trips_through_record_and_undo(&[
+            ("a.txt", "b.txt"),
+            ("b.txt", "c.txt"),
+            ("c.txt", "a.txt"),
+        ]);
+    }
+}
@@ -0,0 +1,95 @@
+// This file is part of vrename.
+// Copyright (C) 2024 John DiSanti.
+//
+// vrename is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// vrename is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with vrename.
+// If not, see <https://www.gnu.org/licenses/>.
+
+//! Detects a file's real type from its magic bytes and proposes a corrected extension,
+//! for `--fix-extensions`.
+
+use anyhow::{anyhow, Result};
+use std::{fs::File, io::Read, path::Path};
+
+/// Number of leading bytes read to sniff a file's type. Large enough for every
+/// signature below, with room to spare.
+const SNIFF_LEN: usize = 4096;
+
+/// Magic-number signature to canonical extension, checked in order.
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (&[0x89, 0x50, 0x4E, 0x47], "png"),
+    (&[0xFF, 0xD8, 0xFF], "jpg"),
+    (b"%PDF", "pdf"),
+    (&[0x1F, 0x8B], "gz"),
+    (&[0x50, 0x4B, 0x03, 0x04], "zip"),
+];
+
+/// Detects the canonical extension for `path`'s content by sniffing its magic bytes.
+/// Returns `None` if the content doesn't match any known signature.
+pub fn detect_extension(path: &Path) -> Result<Option<&'static str>> {
+    let mut file = File::open(path).map_err(|err| anyhow!("failed to open {path:?}: {err}"))?;
+    let mut buf = vec![0u8; SNIFF_LEN];
+    let read = file
+        .read(&mut buf)
+        .map_err(|err| anyhow!("failed to read {path:?}: {err}"))?;
+    let buf = &buf[..read];
+
+    Ok(SIGNATURES
+        .iter()
+        .find(|(signature, _)| buf.starts_with(signature))
+        .map(|(_, extension)| *extension))
+}
+
+/// Proposes a corrected name for `file_name` based on its content's magic bytes.
+/// Returns `file_name` unchanged if its signature is unknown or already matches its
+/// current extension, leaving it untouched for the user to fix up themselves.
+pub fn propose_name(file_name: &str) -> Result<String> {
+    let path = Path::new(file_name);
+    let Some(detected) = detect_extension(path)? else {
+        return Ok(file_name.to_string());
+    };
+    if path.extension().and_then(|ext| ext.to_str()) == Some(detected) {
+        return Ok(file_name.to_string());
+    }
+    Ok(path.with_extension(detected).to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_temp(contents: &[u8], extension: &str) -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("sample.{extension}"));
+        let mut file = File::create(&path).unwrap();
+        file.write_all(contents).unwrap();
+        (dir, path.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn corrects_a_mislabeled_png() {
+        let (_dir, path) = write_temp(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A], "txt");
+        let proposed = propose_name(&path).unwrap();
+        assert!(proposed.ends_with(".png"), "{proposed}");
+    }
+
+    #[test]
+    fn leaves_an_already_correct_extension_alone() {
+        let (_dir, path) = write_temp(&[0x25, b'P', b'D', b'F'], "pdf");
+        assert_eq!(path, propose_name(&path).unwrap());
+    }
+
+    #[test]
+    fn leaves_unknown_signatures_alone() {
+        let (_dir, path) = write_temp(b"just some text", "txt");
+        assert_eq!(path, propose_name(&path).unwrap());
+    }
+}
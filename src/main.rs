@@ -12,25 +12,45 @@
 // You should have received a copy of the GNU General Public License along with vrename.
 // If not, see <https://www.gnu.org/licenses/>.
 
+use crate::editor::EditorCommand;
 use crate::name_file::NameFile;
+use crate::plan::RenamePlan;
 use anyhow::{anyhow, bail, Result};
 use std::{
+    collections::HashMap,
     fs,
     process::{self, Stdio},
 };
 
+mod diff;
+mod editor;
+mod journal;
+mod magic;
 mod name_file;
+mod plan;
+mod substitute;
 
 struct Inputs {
-    preferred_editor: String,
+    preferred_editor: EditorCommand,
     file_names: Vec<String>,
+    dry_run: bool,
+    fix_extensions: bool,
 }
 
 impl Inputs {
     fn from_env() -> Result<Option<Self>> {
-        let preferred_editor = std::env::var("EDITOR")
-            .map_err(|_| anyhow!("missing preferred editor (EDITOR) environment variable"))?;
-        let file_names = std::env::args().skip(1).collect::<Vec<_>>();
+        let preferred_editor = EditorCommand::resolve()?;
+
+        let mut dry_run = false;
+        let mut fix_extensions = false;
+        let mut file_names = Vec::new();
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                "-n" | "--dry-run" => dry_run = true,
+                "--fix-extensions" => fix_extensions = true,
+                _ => file_names.push(arg),
+            }
+        }
 
         Ok(if file_names.is_empty() {
             None
@@ -38,17 +58,30 @@ impl Inputs {
             Some(Self {
                 preferred_editor,
                 file_names,
+                dry_run,
+                fix_extensions,
             })
         })
     }
 }
 
 fn vrename(inputs: &Inputs) -> Result<()> {
-    // Create the temp name file with the names from args
-    let name_file = NameFile::new(&inputs.file_names)?;
+    // Create the temp name file with the names from args, pre-filling extension
+    // corrections as suggestions when `--fix-extensions` is given
+    let name_file = if inputs.fix_extensions {
+        let suggestions = inputs
+            .file_names
+            .iter()
+            .map(|file_name| magic::propose_name(file_name))
+            .collect::<Result<Vec<_>>>()?;
+        NameFile::with_initial_lines(&inputs.file_names, &suggestions)?
+    } else {
+        NameFile::new(&inputs.file_names)?
+    };
 
     // Open that temp file in the preferred editor
-    let output = process::Command::new(&inputs.preferred_editor)
+    let output = process::Command::new(&inputs.preferred_editor.program)
+        .args(&inputs.preferred_editor.args)
         .arg(name_file.path())
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
@@ -62,23 +95,149 @@ fn vrename(inputs: &Inputs) -> Result<()> {
     // Read the names back from the temp file after editing
     let name_map = name_file.read_back()?;
 
-    // Perform the renames
-    for (old_name, new_name) in &name_map {
-        fs::rename(old_name, new_name)
-            .map_err(|err| anyhow!("failed to rename {old_name} to {new_name}: {err}"))?;
-        eprintln!("renamed \"{old_name}\" to \"{new_name}\"");
+    // A dry run must not touch the disk, so preview the renames the user actually
+    // typed instead of computing a real `RenamePlan` — that would create (and then
+    // immediately delete) a real cycle-breaking temp file for any swap or rotation.
+    if inputs.dry_run {
+        let preview_steps = plan::preview_pairs(&name_map)?;
+        print_dry_run_preview(&inputs.file_names, &name_map, &preview_steps);
+        return Ok(());
+    }
+
+    // Compute a collision-safe execution order before touching the disk
+    let plan = RenamePlan::new(&name_map)?;
+    apply_plan(&plan, &name_map)
+}
+
+/// Applies a rename plan to disk and journals it so `--undo` can roll it back later.
+/// Journals `name_map` (the semantic old -> new pairs the user actually asked for)
+/// rather than `plan.steps`, since the latter may route a cycle through a temp name
+/// that no longer exists once this batch finishes.
+fn apply_plan(plan: &RenamePlan, name_map: &HashMap<String, String>) -> Result<()> {
+    for step in &plan.steps {
+        fs::rename(&step.old_name, &step.new_name).map_err(|err| {
+            anyhow!(
+                "failed to rename {} to {}: {err}",
+                step.old_name,
+                step.new_name
+            )
+        })?;
+        eprintln!("renamed \"{}\" to \"{}\"", step.old_name, step.new_name);
+    }
+    journal::record(name_map)?;
+    Ok(())
+}
+
+/// Non-interactive regex substitution mode: `vrename -s <pattern> <replacement>
+/// [--first] <file names...>`. Bypasses the temp-file/editor round trip, but still
+/// runs the result through the same collision-safe rename plan.
+fn do_substitute(args: &[String]) -> Result<()> {
+    let mut args = args.iter();
+    let pattern = args
+        .next()
+        .ok_or_else(|| anyhow!("-s requires a pattern argument"))?;
+    let replacement = args
+        .next()
+        .ok_or_else(|| anyhow!("-s requires a replacement argument"))?;
+
+    let mut replace_first_only = false;
+    let mut file_names = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--first" => replace_first_only = true,
+            _ => file_names.push(arg.clone()),
+        }
+    }
+    if file_names.is_empty() {
+        bail!("-s requires at least one file name");
+    }
+
+    let name_map = substitute::substitute(pattern, replacement, &file_names, replace_first_only)?;
+    let plan = RenamePlan::new(&name_map)?;
+    apply_plan(&plan, &name_map)
+}
+
+/// Reverses the most recently journaled batch of renames. The journal entry is only
+/// removed once every rename in the reversal has actually succeeded, so a failure
+/// partway through (e.g. a file moved out from under us) leaves the history intact
+/// to retry rather than silently losing the batch forever.
+fn undo() -> Result<()> {
+    let Some(entry) = journal::peek_last_entry()? else {
+        eprintln!("nothing to undo");
+        return Ok(());
+    };
+
+    let current_dir = std::env::current_dir()
+        .map_err(|err| anyhow!("failed to determine current directory: {err}"))?
+        .to_string_lossy()
+        .into_owned();
+    if entry.dir != current_dir {
+        bail!(
+            "most recent journaled batch ran in \"{}\", not the current directory",
+            entry.dir
+        );
+    }
+
+    // Reverse new -> old and run it back through the same collision-safe ordering
+    let reversed_map = entry
+        .renames
+        .iter()
+        .cloned()
+        .map(|(old_name, new_name)| (new_name, old_name))
+        .collect::<HashMap<_, _>>();
+    let plan = RenamePlan::new(&reversed_map)?;
+    for step in &plan.steps {
+        fs::rename(&step.old_name, &step.new_name).map_err(|err| {
+            anyhow!(
+                "failed to undo rename of {} to {}: {err}",
+                step.old_name,
+                step.new_name
+            )
+        })?;
+        eprintln!("undid rename: \"{}\" -> \"{}\"", step.old_name, step.new_name);
     }
 
+    journal::remove_last_entry()?;
     Ok(())
 }
 
+/// Prints the renames that would be applied without touching the disk: the renames the
+/// user actually typed first, then a unified-diff-style view of the name-file buffer
+/// in its original order.
+fn print_dry_run_preview(
+    file_names: &[String],
+    name_map: &HashMap<String, String>,
+    preview_steps: &[plan::RenameStep],
+) {
+    println!("planned renames:");
+    for step in preview_steps {
+        println!("  \"{}\" -> \"{}\"", step.old_name, step.new_name);
+    }
+
+    println!();
+    let new_lines = file_names
+        .iter()
+        .map(|old_name| name_map[old_name].clone())
+        .collect::<Vec<_>>();
+    print!("{}", diff::unified_diff(file_names, &new_lines));
+}
+
 fn do_main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("--undo") => return undo(),
+        Some("-s") => return do_substitute(&args[1..]),
+        _ => {}
+    }
+
     match Inputs::from_env()? {
         Some(inputs) => vrename(&inputs),
         None => {
             eprintln!("vrename - batch rename files with your preferred text editor");
             eprintln!();
-            eprintln!("usage: vrename <file names...>");
+            eprintln!("usage: vrename [-n|--dry-run] [--fix-extensions] <file names...>");
+            eprintln!("       vrename -s <pattern> <replacement> [--first] <file names...>");
+            eprintln!("       vrename --undo");
             process::exit(0);
         }
     }
@@ -28,12 +28,24 @@ pub struct NameFile<'a, S> {
 impl<'a, S: AsRef<str> + std::fmt::Debug> NameFile<'a, S> {
     /// Creates the temp file with the given file names in it
     pub fn new(file_names: &'a [S]) -> Result<Self> {
+        let initial_lines = file_names
+            .iter()
+            .map(|file_name| file_name.as_ref().to_string())
+            .collect::<Vec<_>>();
+        Self::with_initial_lines(file_names, &initial_lines)
+    }
+
+    /// Creates the temp file with `file_names` as the old-name identifiers, but
+    /// `initial_lines` as the starting buffer content, so a caller can pre-fill
+    /// suggested names (e.g. `--fix-extensions`) for the user to review before editing.
+    pub fn with_initial_lines(file_names: &'a [S], initial_lines: &[String]) -> Result<Self> {
+        debug_assert_eq!(file_names.len(), initial_lines.len());
         let mut temp_file = tempfile::NamedTempFile::new()
             .map_err(|err| anyhow!("failed to open temp file: {err}"))?;
         {
             let mut temp_writer = BufWriter::new(&mut temp_file);
-            for file_name in file_names {
-                writeln!(temp_writer, "{}", file_name.as_ref())
+            for initial_line in initial_lines {
+                writeln!(temp_writer, "{initial_line}")
                     .map_err(|err| anyhow!("failed to write to temp file: {err}"))?;
             }
         }
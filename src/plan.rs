@@ -0,0 +1,291 @@
+// This file is part of vrename.
+// Copyright (C) 2024 John DiSanti.
+//
+// vrename is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// vrename is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with vrename.
+// If not, see <https://www.gnu.org/licenses/>.
+
+//! Computes a collision-safe execution order for a batch of renames.
+//!
+//! Applying a rename map in arbitrary order can clobber a file that's still needed as
+//! another rename's source (e.g. swapping `a` and `b`, or rotating `a -> b -> c -> a`).
+//! [`RenamePlan`] treats every rename as a node in a graph, with an edge from R1 to R2
+//! when R1's target path is R2's source path (meaning R2 must run first so R1 doesn't
+//! overwrite a file still needed as a source), then linearizes that graph. Cycles are
+//! broken by routing one rename through a temporary name in the same directory.
+
+use anyhow::{anyhow, bail, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tempfile::TempPath;
+
+/// A single rename to apply, already ordered so it's safe to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameStep {
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// An ordered, collision-checked plan for applying a batch of renames.
+pub struct RenamePlan {
+    pub steps: Vec<RenameStep>,
+    // Keeps any cycle-breaking temp files alive until their steps execute.
+    _temp_guards: Vec<TempPath>,
+}
+
+impl RenamePlan {
+    /// Builds a safe execution order from a map of old name to new name.
+    ///
+    /// Fails if two different old names are renamed to the same new name. Prints a
+    /// warning (but doesn't fail) if a target already exists on disk outside of this
+    /// batch, since that's likely a mistake but not necessarily a fatal one.
+    pub fn new(name_map: &HashMap<String, String>) -> Result<Self> {
+        reject_duplicate_targets(name_map)?;
+        warn_about_existing_targets(name_map);
+
+        // Renames that don't actually change the name are a no-op, and including them
+        // would make every one of them look like a one-node cycle.
+        let name_map = name_map
+            .iter()
+            .filter(|(old_name, new_name)| old_name != new_name)
+            .map(|(old_name, new_name)| (old_name.clone(), new_name.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let mut steps = Vec::with_capacity(name_map.len());
+        let mut temp_guards = Vec::new();
+        let mut visited = HashSet::new();
+
+        // Sort so that the plan is deterministic.
+        let mut old_names = name_map.keys().cloned().collect::<Vec<_>>();
+        old_names.sort();
+
+        for start in old_names {
+            if visited.contains(&start) {
+                continue;
+            }
+            // Follow the chain of renames starting here: old -> new -> (new's rename, if
+            // any) -> ... until it either runs off the end (a plain chain) or loops back
+            // on the starting node (a cycle).
+            let mut chain = vec![start.clone()];
+            visited.insert(start.clone());
+            loop {
+                let next = &name_map[chain.last().unwrap()];
+                if *next == chain[0] {
+                    push_cycle_steps(&chain, &name_map, &mut steps, &mut temp_guards)?;
+                    break;
+                }
+                if name_map.contains_key(next) && !visited.contains(next) {
+                    visited.insert(next.clone());
+                    chain.push(next.clone());
+                } else {
+                    push_chain_steps(&chain, &name_map, &mut steps);
+                    break;
+                }
+            }
+        }
+
+        Ok(Self {
+            steps,
+            _temp_guards: temp_guards,
+        })
+    }
+}
+
+/// Returns the semantic old -> new pairs a `name_map` would rename, for previewing to
+/// the user. Unlike [`RenamePlan::new`], this never touches disk: cycles are left
+/// exactly as the user typed them (a swap shows as `a -> b` and `b -> a`, not routed
+/// through a temp name) since nothing here will actually be executed.
+pub fn preview_pairs(name_map: &HashMap<String, String>) -> Result<Vec<RenameStep>> {
+    reject_duplicate_targets(name_map)?;
+    warn_about_existing_targets(name_map);
+
+    let mut old_names = name_map
+        .iter()
+        .filter(|(old_name, new_name)| old_name != new_name)
+        .map(|(old_name, _)| old_name.clone())
+        .collect::<Vec<_>>();
+    old_names.sort();
+
+    Ok(old_names
+        .into_iter()
+        .map(|old_name| RenameStep {
+            new_name: name_map[&old_name].clone(),
+            old_name,
+        })
+        .collect())
+}
+
+fn reject_duplicate_targets(name_map: &HashMap<String, String>) -> Result<()> {
+    let mut targets_seen = HashMap::new();
+    for (old_name, new_name) in name_map {
+        if let Some(other_old_name) = targets_seen.insert(new_name.clone(), old_name.clone()) {
+            bail!("both \"{other_old_name}\" and \"{old_name}\" are renamed to \"{new_name}\"");
+        }
+    }
+    Ok(())
+}
+
+fn warn_about_existing_targets(name_map: &HashMap<String, String>) {
+    for new_name in name_map.values() {
+        if !name_map.contains_key(new_name) && Path::new(new_name).exists() {
+            eprintln!("warning: \"{new_name}\" already exists and isn't part of this batch");
+        }
+    }
+}
+
+/// Pushes the steps for a cycle-free chain, in the order they're safe to run: starting
+/// from the tail, whose target is either not another rename's source in this batch or
+/// the source of a rename whose step was already pushed.
+fn push_chain_steps(
+    chain: &[String],
+    name_map: &HashMap<String, String>,
+    steps: &mut Vec<RenameStep>,
+) {
+    for old_name in chain.iter().rev() {
+        steps.push(RenameStep {
+            old_name: old_name.clone(),
+            new_name: name_map[old_name].clone(),
+        });
+    }
+}
+
+/// Breaks a cycle (e.g. a swap or rotation) by renaming its first node to a fresh temp
+/// name, running the rest of the cycle, then moving the temp name into its final place.
+fn push_cycle_steps(
+    chain: &[String],
+    name_map: &HashMap<String, String>,
+    steps: &mut Vec<RenameStep>,
+    temp_guards: &mut Vec<TempPath>,
+) -> Result<()> {
+    let first = &chain[0];
+    let dir = Path::new(first)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let temp_path = tempfile::Builder::new()
+        .prefix(".vrename-")
+        .tempfile_in(dir)
+        .map_err(|err| anyhow!("failed to create temp file to break rename cycle: {err}"))?
+        .into_temp_path();
+    let temp_name = temp_path.to_string_lossy().into_owned();
+    temp_guards.push(temp_path);
+
+    steps.push(RenameStep {
+        old_name: first.clone(),
+        new_name: temp_name.clone(),
+    });
+    for old_name in chain[1..].iter().rev() {
+        steps.push(RenameStep {
+            old_name: old_name.clone(),
+            new_name: name_map[old_name].clone(),
+        });
+    }
+    steps.push(RenameStep {
+        old_name: temp_name,
+        new_name: name_map[first].clone(),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(old, new)| (old.to_string(), new.to_string()))
+            .collect()
+    }
+
+    fn names(steps: &[RenameStep]) -> Vec<(String, String)> {
+        steps
+            .iter()
+            .map(|step| (step.old_name.clone(), step.new_name.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn plans_independent_renames_in_any_safe_order() {
+        let plan = RenamePlan::new(&map(&[("a", "x"), ("b", "y")])).unwrap();
+        let mut got = names(&plan.steps);
+        got.sort();
+        assert_eq!(
+            vec![
+                ("a".to_string(), "x".to_string()),
+                ("b".to_string(), "y".to_string())
+            ],
+            got
+        );
+    }
+
+    #[test]
+    fn orders_a_chain_tail_first() {
+        let plan = RenamePlan::new(&map(&[("a", "b"), ("b", "c"), ("c", "d")])).unwrap();
+        assert_eq!(
+            vec![
+                ("c".to_string(), "d".to_string()),
+                ("b".to_string(), "c".to_string()),
+                ("a".to_string(), "b".to_string()),
+            ],
+            names(&plan.steps)
+        );
+    }
+
+    #[test]
+    fn breaks_a_two_node_swap_with_a_temp_name() {
+        let plan = RenamePlan::new(&map(&[("a", "b"), ("b", "a")])).unwrap();
+        let steps = names(&plan.steps);
+        assert_eq!(3, steps.len());
+        assert_eq!("a", steps[0].0);
+        assert_eq!("b", steps[1].0);
+        assert_eq!("a", steps[1].1);
+        assert_eq!(steps[0].1, steps[2].0);
+        assert_eq!("b", steps[2].1);
+    }
+
+    #[test]
+    fn rejects_two_old_names_mapping_to_the_same_new_name() {
+        let err = RenamePlan::new(&map(&[("a", "c"), ("b", "c")]))
+            .err()
+            .unwrap();
+        assert!(format!("{err}").contains("are renamed to \"c\""));
+    }
+
+    #[test]
+    fn ignores_renames_that_do_not_change_the_name() {
+        let plan = RenamePlan::new(&map(&[("a", "a")])).unwrap();
+        assert!(plan.steps.is_empty());
+    }
+
+    #[test]
+    fn preview_pairs_leaves_a_swap_untouched_by_a_temp_name() {
+        let steps = preview_pairs(&map(&[("a", "b"), ("b", "a")])).unwrap();
+        assert_eq!(
+            vec![
+                ("a".to_string(), "b".to_string()),
+                ("b".to_string(), "a".to_string()),
+            ],
+            names(&steps)
+        );
+    }
+
+    #[test]
+    fn preview_pairs_ignores_renames_that_do_not_change_the_name() {
+        let steps = preview_pairs(&map(&[("a", "a")])).unwrap();
+        assert!(steps.is_empty());
+    }
+
+    #[test]
+    fn preview_pairs_rejects_two_old_names_mapping_to_the_same_new_name() {
+        let err = preview_pairs(&map(&[("a", "c"), ("b", "c")])).err().unwrap();
+        assert!(format!("{err}").contains("are renamed to \"c\""));
+    }
+}
@@ -0,0 +1,104 @@
+// This file is part of vrename.
+// Copyright (C) 2024 John DiSanti.
+//
+// vrename is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// vrename is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with vrename.
+// If not, see <https://www.gnu.org/licenses/>.
+
+//! Non-interactive regex substitution mode (`-s`), for scripting and large batches
+//! where launching an editor is impractical.
+
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Applies `pattern` -> `replacement` to each of `file_names`, returning an old-name to
+/// new-name map suitable for [`crate::plan::RenamePlan`]. Capture groups in `pattern`
+/// can be referenced in `replacement` as `$1` or `${name}`, following `regex`'s own
+/// replacement syntax. Replaces every match per name unless `replace_first_only` is set.
+pub fn substitute(
+    pattern: &str,
+    replacement: &str,
+    file_names: &[String],
+    replace_first_only: bool,
+) -> Result<HashMap<String, String>> {
+    let regex =
+        Regex::new(pattern).map_err(|err| anyhow!("invalid pattern {pattern:?}: {err}"))?;
+
+    let mut name_map = HashMap::with_capacity(file_names.len());
+    for file_name in file_names {
+        let new_name = if replace_first_only {
+            regex.replace(file_name, replacement)
+        } else {
+            regex.replace_all(file_name, replacement)
+        };
+        if new_name.trim().is_empty() {
+            bail!("substitution would rename \"{file_name}\" to an empty name");
+        }
+        name_map.insert(file_name.clone(), new_name.into_owned());
+    }
+    Ok(name_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn replaces_all_matches_by_default() {
+        let name_map = substitute("o", "0", &names(&["foo", "bob"]), false).unwrap();
+        assert_eq!("f00", name_map["foo"]);
+        assert_eq!("b0b", name_map["bob"]);
+    }
+
+    #[test]
+    fn replace_first_only_stops_after_one_match() {
+        let name_map = substitute("o", "0", &names(&["foo"]), true).unwrap();
+        assert_eq!("f0o", name_map["foo"]);
+    }
+
+    #[test]
+    fn supports_numbered_capture_groups() {
+        let name_map = substitute(
+            r"(\w+)-(\d+)\.txt",
+            "$2-$1.txt",
+            &names(&["report-2024.txt"]),
+            false,
+        )
+        .unwrap();
+        assert_eq!("2024-report.txt", name_map["report-2024.txt"]);
+    }
+
+    #[test]
+    fn supports_named_capture_groups() {
+        let name_map = substitute(
+            r"(?P<stem>\w+)\.jpeg",
+            "${stem}.jpg",
+            &names(&["photo.jpeg"]),
+            false,
+        )
+        .unwrap();
+        assert_eq!("photo.jpg", name_map["photo.jpeg"]);
+    }
+
+    #[test]
+    fn rejects_an_invalid_pattern() {
+        assert!(substitute("(", "x", &names(&["a"]), false).is_err());
+    }
+
+    #[test]
+    fn rejects_a_substitution_that_empties_a_name() {
+        assert!(substitute(".*", "", &names(&["a"]), false).is_err());
+    }
+}